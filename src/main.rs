@@ -76,8 +76,8 @@ fn generate_screw_locations() -> [(f32, f32); NUM_SCREWS] {
     }
 }
     
-///Generates the grids of resulting added darkness for each potential line drawn between screws. masks\[0]\[4] returns an image of the line drawn between the top screw and the screw 4 notches clockwise from it.
-fn generate_line_mask(i: usize, j: usize, screws: [(f32, f32); NUM_SCREWS]) -> [[f32; SIZE as usize]; SIZE as usize] {
+///Generates the sparse mask of added darkness for a single potential line drawn between screws `i` and `j`, as a list of `(pixel_index, weight)` pairs for only the pixels the line actually darkens. `pixel_index` is `y * SIZE + x`.
+fn generate_line_mask(i: usize, j: usize, screws: [(f32, f32); NUM_SCREWS]) -> Vec<(u32, f32)> {
     //let mut mask = [[0.0; SIZE as usize]; SIZE as usize];
     //let x1 = screws[i].0 as usize;
     //let y1 = screws[i].1 as usize;
@@ -140,25 +140,43 @@ fn generate_line_mask(i: usize, j: usize, screws: [(f32, f32); NUM_SCREWS]) -> [
 //
     //mask
 
-    let mut mask = [[0.0; SIZE as usize]; SIZE as usize];
+    let mut mask = Vec::new();
     let (x1, y1) = screws[i];
     let (x2, y2) = screws[j];
     let m = (y2 - y1) / (x2 - x1);
     let c = y1 - m * x1;
     for x in 0..SIZE {
         for y in 0..SIZE {
-            //if the pixel is further away from the center of the image than the circle's radius, just make it white
+            //if the pixel is further away from the center of the image than the circle's radius, skip it, it stays white
             if ((x as f32 - SIZE as f32 / 2.0).powi(2) + (y as f32 - SIZE as f32 / 2.0).powi(2) > (SIZE as f32 / 2.0).powi(2)) & CROP_TO_CIRCLE {
-                mask[y as usize][x as usize] = 0.0;
                 continue;
             }
             let distance = (m * x as f32 - y as f32 + c).abs() / (m * m + 1.0).sqrt();
-            mask[y as usize][x as usize] = point_profile(distance);
+            let weight = point_profile(distance);
+            if weight > 0.0 {
+                mask.push((y * SIZE + x, weight));
+            }
         }
     }
     mask
 }
 
+///Precomputes the sparse mask (see `generate_line_mask`) for every valid screw pair so the selection loop never has to rasterize a line twice. Indexed as `masks[i * NUM_SCREWS + j]`; pairs that are too close together to be drawn (see the `NUM_SCREWS / 15` gap check in `main`) map to an empty entry. The mask for `(i, j)` is identical to the mask for `(j, i)`, so both slots are filled from a single rasterization.
+fn precompute_line_masks(screws: [(f32, f32); NUM_SCREWS]) -> Vec<Vec<(u32, f32)>> {
+    let mut masks = vec![Vec::new(); NUM_SCREWS * NUM_SCREWS];
+    for i in 0..NUM_SCREWS {
+        for j in (i + 1)..NUM_SCREWS {
+            if (j as i32 - i as i32).abs() <= NUM_SCREWS as i32 / 15 {
+                continue;
+            }
+            let mask = generate_line_mask(i, j, screws);
+            masks[j * NUM_SCREWS + i] = mask.clone();
+            masks[i * NUM_SCREWS + j] = mask;
+        }
+    }
+    masks
+}
+
 //fn luminance_to_ansi(luminance: f32) -> u8 {
 //    let chart: Vec<u8> = vec![
 //        0,
@@ -194,11 +212,11 @@ fn generate_line_mask(i: usize, j: usize, screws: [(f32, f32); NUM_SCREWS]) -> [
 //
 //}
 
-/// Takes a grid of floats and prints them based on a luminance chart.
-fn print_image(buffer: &mut Vec<u32>, image: [[f32; SIZE as usize]; SIZE as usize]) {
+/// Takes a flat, row-major (`y * SIZE + x`) grid of floats and prints them based on a luminance chart.
+fn print_image(buffer: &mut Vec<u32>, image: &[f32]) {
     for y in 0..SIZE {
         for x in 0..SIZE {
-            let pixel = image[y as usize][x as usize];
+            let pixel = image[(y * SIZE + x) as usize];
             //let prev_pixel = prev_image[y as usize][x as usize];
             let luminance = (pixel * 255.0) as u8;//luminance_to_ansi(pixel);
             //let prev_luminance = luminance_to_ansi(prev_pixel);
@@ -269,11 +287,11 @@ fn main() {
         } else {
             image
         };
-        let mut grid: [[f32; SIZE as usize]; SIZE as usize] = [[0.0; SIZE as usize]; SIZE as usize];
+        let mut grid: Vec<f32> = vec![0.0; (SIZE * SIZE) as usize];
         for x in 0..SIZE {
             for y in 0..SIZE {
                 let pixel = image.get_pixel(x, y)[0];
-                grid[y as usize][x as usize] = pixel as f32 / 255.0;
+                grid[(y * SIZE + x) as usize] = pixel as f32 / 255.0;
             }
         }
         grid
@@ -281,15 +299,21 @@ fn main() {
 
     
 
-    let mut image: [[f32; SIZE as usize]; SIZE as usize] = [[0.0; SIZE as usize]; SIZE as usize];
+    let mut image: Vec<f32> = vec![0.0; (SIZE * SIZE) as usize];
     let mut i = 100;
-    let mut old_closeness: f32 = 100000.0;
     let screw_locations = generate_screw_locations();
+    //one-time precompute of every valid line's sparse mask, so the selection loop below only ever touches the pixels a candidate line actually darkens
+    let line_masks = precompute_line_masks(screw_locations);
+    //running total squared error between `image` and `ideal_image`, kept in sync incrementally instead of being recomputed from scratch every iteration
+    let mut total_sse: f32 = ideal_image.iter().zip(image.iter())
+        .map(|(ideal, pixel)| (ideal - pixel).powi(2))
+        .sum();
 
 
     while window.is_open() {
+        //permutations is the list of the different lines drawn between screws and the resulting change in squared error versus the ideal image. Lower (more negative) is closer to the ideal image.
         let mut permutations: Vec<(usize, usize, f32)> = Vec::new();
-        
+
         for i in {
             if SLOW_BETTER_MODE {
                 0..NUM_SCREWS
@@ -297,44 +321,21 @@ fn main() {
                 i..i+1
             }
         } {
-            //permutations is the list of the different lines drawn between screws and the resulting change in closeness to the ideal image. Higher third value means closer to the ideal image.
             for j in 0..NUM_SCREWS {
                 //if there are at least 2 pegs between the screws, draw the line
                 if (j as i32 - i as i32).abs() > NUM_SCREWS as i32 / 15 {
-                    let mask = generate_line_mask(i, j, screw_locations);
-                    //print_image(&mut buffer, mask);
-                    //window
-                    //    .update_with_buffer(&buffer, (SIZE+SIDE_PADDING*2) as usize, SIZE as usize)
-                    //    .unwrap();
-                    let mut temp_image = image;
-                    for x in 0..SIZE {
-                        for y in 0..SIZE {
-                            temp_image[y as usize][x as usize] += mask[y as usize][x as usize];
-                        }
+                    let mask = &line_masks[i * NUM_SCREWS + j];
+                    //the change in total squared error from drawing this line, computed only over the pixels it touches
+                    let mut delta_sse = 0.0;
+                    for &(pixel_index, weight) in mask {
+                        let residual = ideal_image[pixel_index as usize] - image[pixel_index as usize];
+                        delta_sse += (residual - weight).powi(2) - residual.powi(2);
                     }
-                    //if temp_image == image {
-                    //    continue;
-                    //}
-                    let mut closeness = 0.0;
-                    let mut skip = false;
-                    for x in 0..SIZE {
-                        for y in 0..SIZE {
-                            closeness += (ideal_image[y as usize][x as usize] - image[y as usize][x as usize] - mask[y as usize][x as usize]).powi(2);
-                            if old_closeness - closeness < DELTA_DIFF_THRESHOLD {
-                                skip = true;
-                                //println!("skipped");
-                                break;
-                            }
-                        }
-                        if skip {
-                            //println!("skipped");
-                            break;
-                        }
+                    if delta_sse > -DELTA_DIFF_THRESHOLD {
+                        //println!("skipped");
+                        continue;
                     }
-                    if !skip {
-                        permutations.push((i, j, closeness));
-                    }
-                    //std::thread::sleep(Duration::from_millis(100));
+                    permutations.push((i, j, delta_sse));
                 }
             }
         }
@@ -344,48 +345,30 @@ fn main() {
         if permutations.len() == 0 {
             break;
         }
-        
-        
-        let mut closeness = permutations[0].2;
-        let mut x = 0;
-        while old_closeness - closeness < DELTA_DIFF_THRESHOLD {
-            println!("{}, {}, {}", closeness, old_closeness, old_closeness - closeness);
-            x += 1;
-            closeness = permutations[x].2;
-            if x >= permutations.len() - 1 {
-                break;
-            }
-        }
-        if x != 0 {
-            panic!("x is not 0");
-        }
-        //println!("{}, {}, {}, {}, {}", permutations[x].2, old_closeness, permutations.len(), x, old_closeness - closeness);
-        old_closeness = closeness;
-        let mask;
-        if SLOW_BETTER_MODE {
-            mask = generate_line_mask(permutations[x].1, permutations[x].0, screw_locations);
-        } else {
-            i = permutations[x].1;
-            mask = generate_line_mask(i, permutations[x].0, screw_locations);
-        }
+
+        let (best_i, best_j, best_delta) = permutations[0];
+        total_sse += best_delta;
         //apply the line to the image
-        for x in 0..SIZE {
-            for y in 0..SIZE {
-                image[y as usize][x as usize] += mask[y as usize][x as usize];
-            }
+        let mask = &line_masks[best_i * NUM_SCREWS + best_j];
+        for &(pixel_index, weight) in mask {
+            image[pixel_index as usize] += weight;
         }
-        print_image(&mut buffer, image);
+        if !SLOW_BETTER_MODE {
+            i = best_j;
+        }
+
+        print_image(&mut buffer, &image);
         window
             .update_with_buffer(&buffer, (SIZE+SIDE_PADDING*2) as usize, SIZE as usize)
             .unwrap();
     }
-    println!("done");
+    println!("done, total squared error: {}", total_sse);
     //save the image
     let mut save_image = DynamicImage::new_luma8(SIZE, SIZE)
         .to_luma8();
     for x in 0..SIZE {
         for y in 0..SIZE {
-            save_image.put_pixel(x, y, image::Luma([(image[y as usize][x as usize] * 255.0) as u8]));
+            save_image.put_pixel(x, y, image::Luma([(image[(y * SIZE + x) as usize] * 255.0) as u8]));
         }
     }
     save_image.save("output.png").unwrap();